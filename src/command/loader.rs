@@ -1,24 +1,45 @@
 use anyhow::{Context, Result};
+use handlebars::Handlebars;
+use notify::{RecursiveMode, Watcher};
+use serde_json::Value;
 use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
+use std::sync::mpsc as std_mpsc;
+use std::sync::{Arc, RwLock};
+use std::thread;
 
+use super::plugin::PluginClient;
 use super::types::Command;
 
 pub struct CommandRegistry {
-    commands: HashMap<String, Command>,
+    commands: RwLock<HashMap<String, Command>>,
+    templates: RwLock<Handlebars<'static>>,
 }
 
 impl CommandRegistry {
     pub fn new() -> Self {
+        let mut templates = Handlebars::new();
+        templates.set_strict_mode(false);
+        templates.register_escape_fn(handlebars::no_escape);
+
         Self {
-            commands: HashMap::new(),
+            commands: RwLock::new(HashMap::new()),
+            templates: RwLock::new(templates),
         }
     }
 
-    pub fn load_from_dir<P: AsRef<Path>>(&mut self, dir: P) -> Result<()> {
+    /// (Re)loads every `*.yaml` command in `dir`, replacing the current set.
+    /// Safe to call again at runtime: existing tools are torn down and
+    /// reloaded from scratch, so renames and removals are picked up too.
+    pub fn load_from_dir<P: AsRef<Path>>(&self, dir: P) -> Result<()> {
         let dir_path = dir.as_ref();
 
+        let mut commands = HashMap::new();
+        let mut templates = Handlebars::new();
+        templates.set_strict_mode(false);
+        templates.register_escape_fn(handlebars::no_escape);
+
         for entry in fs::read_dir(dir_path)
             .context("Failed to read commands directory")?
         {
@@ -32,19 +53,272 @@ impl CommandRegistry {
                 let command: Command = serde_yaml::from_str(&content)
                     .with_context(|| format!("Failed to parse {:?}", path))?;
 
-                eprintln!("Loaded command: {}", command.name);
-                self.commands.insert(command.name.clone(), command);
+                if let Some(plugin_path) = command.plugin.clone() {
+                    Self::load_plugin(&mut commands, command, &plugin_path);
+                    continue;
+                }
+
+                templates
+                    .register_template_string(&command.name, &command.prompt)
+                    .with_context(|| format!("Failed to register template for {}", command.name))?;
+
+                eprintln!("Loaded command: {} v{}", command.name, command.version);
+                commands.insert(command.name.clone(), command);
             }
         }
 
+        *self.commands.write().unwrap() = commands;
+        *self.templates.write().unwrap() = templates;
+
         Ok(())
     }
 
-    pub fn get(&self, name: &str) -> Option<&Command> {
-        self.commands.get(name)
+    /// Watches `dir` for `*.yaml` changes and reloads commands whenever the
+    /// set of tool names changes, invoking `on_change` so the caller can
+    /// announce `notifications/tools/list_changed`.
+    pub fn watch(
+        self: &Arc<Self>,
+        dir: impl AsRef<Path>,
+        on_change: impl Fn() + Send + 'static,
+    ) -> Result<()> {
+        let dir = dir.as_ref().to_path_buf();
+        let registry = Arc::clone(self);
+        let (tx, rx) = std_mpsc::channel();
+
+        let mut watcher = notify::recommended_watcher(tx).context("Failed to start file watcher")?;
+        watcher
+            .watch(&dir, RecursiveMode::NonRecursive)
+            .with_context(|| format!("Failed to watch {:?}", dir))?;
+
+        thread::spawn(move || {
+            // Keep the watcher alive for the life of the thread.
+            let _watcher = watcher;
+            let mut known: Vec<String> = registry.list().iter().map(|cmd| cmd.name.clone()).collect();
+            known.sort();
+
+            while let Ok(event) = rx.recv() {
+                if !Self::is_yaml_event(&event) {
+                    continue;
+                }
+
+                if let Err(err) = registry.load_from_dir(&dir) {
+                    eprintln!("Failed to reload commands from {:?}: {:#}", dir, err);
+                    continue;
+                }
+
+                let mut current: Vec<String> =
+                    registry.list().iter().map(|cmd| cmd.name.clone()).collect();
+                current.sort();
+
+                if current != known {
+                    known = current;
+                    on_change();
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    fn is_yaml_event(event: &notify::Result<notify::Event>) -> bool {
+        match event {
+            Ok(event) => event
+                .paths
+                .iter()
+                .any(|path| path.extension().and_then(|s| s.to_str()) == Some("yaml")),
+            Err(_) => false,
+        }
+    }
+
+    /// Runs the discovery handshake against a plugin process and registers
+    /// each tool it advertises as its own command entry, templated off
+    /// `template` (its YAML stanza minus name/description/schema).
+    fn load_plugin(commands: &mut HashMap<String, Command>, template: Command, plugin_path: &str) {
+        let tools = match PluginClient::new(plugin_path).discover() {
+            Ok(tools) => tools,
+            Err(err) => {
+                eprintln!("Plugin discovery failed for {}: {:#}", plugin_path, err);
+                return;
+            }
+        };
+
+        for tool in tools {
+            let mut command = template.clone();
+            command.name = tool.name.clone();
+            command.description = tool.description;
+            command.schema = Some(tool.input_schema);
+
+            eprintln!("Loaded plugin tool: {}", command.name);
+            commands.insert(command.name.clone(), command);
+        }
     }
 
-    pub fn list(&self) -> Vec<&Command> {
-        self.commands.values().collect()
+    /// Merges tools discovered from a downstream MCP server into the
+    /// registry as synthetic commands routed to `downstream_id`.
+    pub fn register_downstream_tools(&self, downstream_id: &str, tools: Vec<super::types::DownstreamTool>) {
+        let mut commands = self.commands.write().unwrap();
+
+        for tool in tools {
+            let command = Command {
+                name: tool.name.clone(),
+                version: "0.0.0".to_string(),
+                description: tool.description,
+                parameters: Vec::new(),
+                prompt: String::new(),
+                plugin: None,
+                schema: Some(tool.input_schema),
+                downstream: Some(downstream_id.to_string()),
+            };
+
+            eprintln!("Loaded downstream tool: {}", command.name);
+            commands.insert(command.name.clone(), command);
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<Command> {
+        self.commands.read().unwrap().get(name).cloned()
+    }
+
+    pub fn list(&self) -> Vec<Command> {
+        self.commands.read().unwrap().values().cloned().collect()
+    }
+
+    /// Renders `name`'s prompt template against `arguments`, filling in
+    /// `Parameter::default` for any missing optional values. Returns the
+    /// names of any missing required parameters as an error instead of
+    /// rendering a partial prompt.
+    pub fn render(&self, name: &str, arguments: &Value) -> Result<String, Vec<String>> {
+        let commands = self.commands.read().unwrap();
+        let command = match commands.get(name) {
+            Some(command) => command,
+            None => return Err(vec![format!("Unknown tool: {}", name)]),
+        };
+
+        let mut missing = Vec::new();
+        let mut context = arguments.clone();
+        if !context.is_object() {
+            context = Value::Object(serde_json::Map::new());
+        }
+        let context_map = context.as_object_mut().expect("context coerced to object above");
+
+        for param in &command.parameters {
+            let has_value = context_map
+                .get(&param.name)
+                .map(|v| !v.is_null())
+                .unwrap_or(false);
+
+            if has_value {
+                continue;
+            }
+
+            if let Some(default) = param.default_value() {
+                context_map.insert(param.name.clone(), default);
+            } else if param.required {
+                missing.push(param.name.clone());
+            }
+        }
+
+        if !missing.is_empty() {
+            return Err(missing);
+        }
+
+        self.templates
+            .read()
+            .unwrap()
+            .render(name, &context)
+            .map_err(|err| vec![err.to_string()])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::types::{Command, Parameter};
+    use serde_json::json;
+
+    fn registry_with(command: Command) -> CommandRegistry {
+        let registry = CommandRegistry::new();
+        registry
+            .templates
+            .write()
+            .unwrap()
+            .register_template_string(&command.name, &command.prompt)
+            .unwrap();
+        registry.commands.write().unwrap().insert(command.name.clone(), command);
+        registry
+    }
+
+    fn command(name: &str, prompt: &str, parameters: Vec<Parameter>) -> Command {
+        Command {
+            name: name.to_string(),
+            version: "1.0.0".to_string(),
+            description: String::new(),
+            parameters,
+            prompt: prompt.to_string(),
+            plugin: None,
+            schema: None,
+            downstream: None,
+        }
+    }
+
+    fn parameter(name: &str, required: bool, default: Option<&str>) -> Parameter {
+        typed_parameter(name, "string", required, default)
+    }
+
+    fn typed_parameter(name: &str, param_type: &str, required: bool, default: Option<&str>) -> Parameter {
+        Parameter {
+            name: name.to_string(),
+            param_type: param_type.to_string(),
+            description: String::new(),
+            required,
+            default: default.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn render_fills_in_missing_defaults() {
+        let registry = registry_with(command(
+            "greet",
+            "Hello {{name}}, loud={{loud}}",
+            vec![
+                parameter("name", true, None),
+                parameter("loud", false, Some("false")),
+            ],
+        ));
+
+        let text = registry.render("greet", &json!({ "name": "Ada" })).unwrap();
+        assert_eq!(text, "Hello Ada, loud=false");
+    }
+
+    #[test]
+    fn render_reports_missing_required_parameters() {
+        let registry = registry_with(command(
+            "greet",
+            "Hello {{name}}",
+            vec![parameter("name", true, None)],
+        ));
+
+        let missing = registry.render("greet", &json!({})).unwrap_err();
+        assert_eq!(missing, vec!["name".to_string()]);
+    }
+
+    #[test]
+    fn render_treats_false_boolean_default_as_falsy() {
+        let registry = registry_with(command(
+            "greet",
+            "{{#if loud}}LOUD{{else}}quiet{{/if}}",
+            vec![typed_parameter("loud", "boolean", false, Some("false"))],
+        ));
+
+        let text = registry.render("greet", &json!({})).unwrap();
+        assert_eq!(text, "quiet");
+    }
+
+    #[test]
+    fn render_does_not_html_escape_arguments() {
+        let registry = registry_with(command("echo", "{{text}}", vec![parameter("text", true, None)]));
+
+        let text = registry.render("echo", &json!({ "text": "<b>Ada & Grace</b>" })).unwrap();
+        assert_eq!(text, "<b>Ada & Grace</b>");
     }
 }
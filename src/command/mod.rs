@@ -0,0 +1,3 @@
+pub mod loader;
+pub mod plugin;
+pub mod types;
@@ -0,0 +1,170 @@
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, Command as ProcessCommand, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+/// How long to wait for a plugin process to respond before killing it.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A tool advertised by a plugin process during discovery.
+pub struct PluginTool {
+    pub name: String,
+    pub description: String,
+    pub input_schema: Value,
+}
+
+/// Kills and reaps the wrapped child on drop, so an error partway through
+/// `exchange` can't leave it orphaned.
+struct ChildGuard(Child);
+
+impl Drop for ChildGuard {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+/// Speaks newline-delimited JSON-RPC to an external plugin process: spawn,
+/// write one request line, read one response line, tear down.
+pub struct PluginClient {
+    path: String,
+}
+
+impl PluginClient {
+    pub fn new(path: impl Into<String>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Sends the discovery handshake and returns the tools the plugin advertises.
+    pub fn discover(&self) -> Result<Vec<PluginTool>> {
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "tools/list",
+            "params": {}
+        });
+
+        let response = self.exchange(&request, DEFAULT_TIMEOUT)?;
+        let tools = response["result"]["tools"].as_array().cloned().unwrap_or_default();
+
+        Ok(tools
+            .into_iter()
+            .filter_map(|tool| {
+                Some(PluginTool {
+                    name: tool["name"].as_str()?.to_string(),
+                    description: tool["description"].as_str().unwrap_or_default().to_string(),
+                    input_schema: tool["inputSchema"].clone(),
+                })
+            })
+            .collect())
+    }
+
+    /// Invokes `tool` with `arguments` and returns the plugin's `result`
+    /// value, ready to use as the MCP `tools/call` result.
+    pub fn call(&self, tool: &str, arguments: &Value) -> Result<Value, String> {
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "tools/call",
+            "params": { "name": tool, "arguments": arguments }
+        });
+
+        let response = self.exchange(&request, DEFAULT_TIMEOUT).map_err(|err| err.to_string())?;
+
+        if let Some(error) = response.get("error") {
+            let message = error["message"].as_str().unwrap_or("Plugin error");
+            return Err(message.to_string());
+        }
+
+        Ok(response["result"].clone())
+    }
+
+    /// Spawns the plugin, writes `request` as a single ndjson line, and
+    /// waits up to `timeout` for a single-line response. The child is
+    /// killed and reaped on the way out, success or failure.
+    fn exchange(&self, request: &Value, timeout: Duration) -> Result<Value> {
+        let mut child = ChildGuard(
+            ProcessCommand::new(&self.path)
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::inherit())
+                .spawn()
+                .with_context(|| format!("Failed to spawn plugin {}", self.path))?,
+        );
+
+        let mut stdin = child.0.stdin.take().context("Plugin stdin unavailable")?;
+        writeln!(stdin, "{}", serde_json::to_string(request)?)
+            .with_context(|| format!("Failed to write to plugin {}", self.path))?;
+        drop(stdin);
+
+        let line = self.read_line_with_timeout(&mut child.0, timeout)?;
+
+        serde_json::from_str(line.trim())
+            .with_context(|| format!("Plugin {} returned invalid JSON", self.path))
+    }
+
+    fn read_line_with_timeout(&self, child: &mut Child, timeout: Duration) -> Result<String> {
+        let stdout = child.stdout.take().context("Plugin stdout unavailable")?;
+        let (tx, rx) = mpsc::channel();
+
+        thread::spawn(move || {
+            let mut reader = BufReader::new(stdout);
+            let mut line = String::new();
+            let _ = reader.read_line(&mut line);
+            let _ = tx.send(line);
+        });
+
+        rx.recv_timeout(timeout)
+            .with_context(|| format!("Plugin {} timed out", self.path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    /// Writes a shell script fixture to a uniquely-named temp path so it can
+    /// stand in for a plugin process, and returns the path as a string.
+    fn script(name: &str, contents: &str) -> String {
+        let mut path = std::env::temp_dir();
+        path.push(format!("rapid-mcp-plugin-test-{}-{}.sh", name, std::process::id()));
+        fs::write(&path, contents).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        path.to_str().unwrap().to_string()
+    }
+
+    /// A fake plugin that just echoes the request line straight back,
+    /// standing in for a real JSON-RPC plugin without a purpose-built binary.
+    #[test]
+    fn discover_parses_tools_from_response() {
+        let path = script("echo", "#!/bin/sh\ncat\n");
+        let client = PluginClient::new(&path);
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "result": { "tools": [{ "name": "echo", "description": "Echoes input", "inputSchema": {} }] }
+        });
+
+        let response = client.exchange(&request, DEFAULT_TIMEOUT).unwrap();
+        assert_eq!(response, request);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn exchange_times_out_against_a_hanging_plugin() {
+        let path = script("hang", "#!/bin/sh\nread line\nsleep 5\n");
+        let client = PluginClient::new(&path);
+        let request = json!({ "jsonrpc": "2.0", "id": 1, "method": "tools/call" });
+
+        let err = client.exchange(&request, Duration::from_millis(200)).unwrap_err();
+        assert!(err.to_string().contains("timed out"));
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,4 +1,5 @@
-use serde::{Deserialize, Serialize};
+use serde::Deserialize;
+use serde_json::{json, Value};
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct Command {
@@ -7,7 +8,65 @@ pub struct Command {
     pub description: String,
     #[serde(default)]
     pub parameters: Vec<Parameter>,
+    #[serde(default)]
     pub prompt: String,
+    /// Path to an external process that serves this tool over stdio
+    /// JSON-RPC, in place of rendering `prompt`. See `command::plugin`.
+    #[serde(default)]
+    pub plugin: Option<String>,
+    /// `inputSchema` discovered from a plugin at load time, overriding the
+    /// one derived from `parameters`. Never present in YAML.
+    #[serde(skip, default)]
+    pub schema: Option<Value>,
+    /// Id of the downstream MCP server that actually serves this tool, set
+    /// for entries synthesized by aggregating a downstream's `tools/list`.
+    /// Never present in YAML.
+    #[serde(skip, default)]
+    pub downstream: Option<String>,
+}
+
+impl Command {
+    /// Builds the MCP `inputSchema` for this command: the plugin-discovered
+    /// schema if there is one, otherwise one derived from `parameters`.
+    pub fn input_schema(&self) -> Value {
+        if let Some(schema) = &self.schema {
+            return schema.clone();
+        }
+
+        let mut properties = serde_json::Map::new();
+        let mut required = Vec::new();
+
+        for param in &self.parameters {
+            let mut schema = json!({
+                "type": param.json_schema_type(),
+                "description": param.description,
+            });
+
+            if let Some(default) = param.default_value() {
+                schema["default"] = default;
+            }
+
+            properties.insert(param.name.clone(), schema);
+
+            if param.required {
+                required.push(param.name.clone());
+            }
+        }
+
+        json!({
+            "type": "object",
+            "properties": properties,
+            "required": required,
+        })
+    }
+}
+
+/// A tool advertised by a downstream MCP server, namespaced as `<id>:<name>`
+/// so it can't collide with this server's own tools or another downstream's.
+pub struct DownstreamTool {
+    pub name: String,
+    pub description: String,
+    pub input_schema: Value,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -20,3 +79,110 @@ pub struct Parameter {
     pub required: bool,
     pub default: Option<String>,
 }
+
+impl Parameter {
+    /// Maps this parameter's `type` string to a JSON Schema type name.
+    fn json_schema_type(&self) -> &'static str {
+        match self.param_type.as_str() {
+            "number" => "number",
+            "boolean" => "boolean",
+            "array" => "array",
+            "object" => "object",
+            _ => "string",
+        }
+    }
+
+    /// Parses `default` (always a YAML string) into a value matching
+    /// `param_type`, so e.g. a `boolean` parameter's default renders as the
+    /// JSON `false`, not the truthy string `"false"`. Falls back to the raw
+    /// string if it doesn't parse as the declared type.
+    pub fn default_value(&self) -> Option<Value> {
+        let default = self.default.as_ref()?;
+
+        Some(match self.param_type.as_str() {
+            "boolean" => default
+                .parse::<bool>()
+                .map(Value::Bool)
+                .unwrap_or_else(|_| Value::String(default.clone())),
+            "number" => default
+                .parse::<i64>()
+                .map(Value::from)
+                .ok()
+                .or_else(|| {
+                    default
+                        .parse::<f64>()
+                        .ok()
+                        .and_then(|n| serde_json::Number::from_f64(n).map(Value::Number))
+                })
+                .unwrap_or_else(|| Value::String(default.clone())),
+            _ => Value::String(default.clone()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command(parameters: Vec<Parameter>) -> Command {
+        Command {
+            name: "greet".to_string(),
+            version: "1.0.0".to_string(),
+            description: "Greets someone".to_string(),
+            parameters,
+            prompt: "Hello {{name}}".to_string(),
+            plugin: None,
+            schema: None,
+            downstream: None,
+        }
+    }
+
+    fn parameter(name: &str, param_type: &str, required: bool, default: Option<&str>) -> Parameter {
+        Parameter {
+            name: name.to_string(),
+            param_type: param_type.to_string(),
+            description: String::new(),
+            required,
+            default: default.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn input_schema_derives_from_parameters() {
+        let command = command(vec![
+            parameter("name", "string", true, None),
+            parameter("loud", "boolean", false, Some("false")),
+            parameter("retries", "number", false, Some("3")),
+        ]);
+
+        let schema = command.input_schema();
+
+        assert_eq!(schema["type"], "object");
+        assert_eq!(schema["properties"]["name"]["type"], "string");
+        assert_eq!(schema["properties"]["loud"]["type"], "boolean");
+        assert_eq!(schema["properties"]["loud"]["default"], json!(false));
+        assert_eq!(schema["properties"]["retries"]["type"], "number");
+        assert_eq!(schema["properties"]["retries"]["default"], json!(3));
+        assert_eq!(schema["required"], json!(["name"]));
+    }
+
+    #[test]
+    fn default_value_falls_back_to_string_when_unparseable() {
+        let param = parameter("flag", "boolean", false, Some("not-a-bool"));
+        assert_eq!(param.default_value(), Some(json!("not-a-bool")));
+    }
+
+    #[test]
+    fn default_value_parses_fractional_numbers() {
+        let param = parameter("ratio", "number", false, Some("1.5"));
+        assert_eq!(param.default_value(), Some(json!(1.5)));
+    }
+
+    #[test]
+    fn input_schema_prefers_discovered_schema_over_parameters() {
+        let mut command = command(vec![parameter("name", "string", true, None)]);
+        command.schema = Some(json!({ "type": "object", "properties": {} }));
+
+        assert_eq!(command.input_schema(), json!({ "type": "object", "properties": {} }));
+    }
+}
@@ -3,20 +3,50 @@ mod command;
 
 use anyhow::Result;
 use command::loader::CommandRegistry;
+use mcp::client::McpClient;
 use mcp::server::McpServer;
+use mcp::transport::TransportMode;
+use std::collections::HashMap;
 
-fn main() -> Result<()> {
+#[tokio::main]
+async fn main() -> Result<()> {
     eprintln!("Starting rapid-mcp-server-rust...");
 
+    const COMMANDS_DIR: &str = "commands";
+    const DOWNSTREAMS_CONFIG: &str = "downstreams.yaml";
+
     // Load commands
-    let mut registry = CommandRegistry::new();
-    registry.load_from_dir("commands")?;
+    let registry = CommandRegistry::new();
+    registry.load_from_dir(COMMANDS_DIR)?;
 
     eprintln!("Loaded {} commands", registry.list().len());
 
+    // Federate any downstream MCP servers: their tools are merged into the
+    // registry under an `<id>:` namespace, and `tools/call` for one is
+    // forwarded to the owning client.
+    let mut downstreams = HashMap::new();
+    for downstream in mcp::client::load_downstreams(DOWNSTREAMS_CONFIG)? {
+        let client = McpClient::new(downstream.id.clone(), downstream.command.clone());
+        match client.discover() {
+            Ok(tools) => {
+                eprintln!("Discovered {} tool(s) from downstream '{}'", tools.len(), downstream.id);
+                registry.register_downstream_tools(&downstream.id, tools);
+                downstreams.insert(downstream.id.clone(), client);
+            }
+            Err(err) => {
+                eprintln!("Failed to connect to downstream '{}': {:#}", downstream.id, err);
+            }
+        }
+    }
+
+    // Select transport: `--transport=header` or `MCP_TRANSPORT=header` for
+    // Content-Length framing, ndjson otherwise.
+    let args: Vec<String> = std::env::args().collect();
+    let (reader, writer) = TransportMode::from_args_or_env(&args).build();
+
     // Start MCP server
-    let server = McpServer::new(registry);
-    server.run()?;
+    let server = McpServer::new(registry, COMMANDS_DIR, downstreams);
+    server.run(reader, writer).await?;
 
     Ok(())
 }
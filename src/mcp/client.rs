@@ -0,0 +1,274 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::process::{Child, ChildStdin, Command as ProcessCommand, Stdio};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::command::types::DownstreamTool;
+
+/// How long to wait for a downstream to answer a single request before
+/// treating the connection as hung. Shorter under test so a hung-downstream
+/// test doesn't have to sit through a real 10-second wait.
+#[cfg(not(test))]
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+#[cfg(test)]
+const DEFAULT_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// One entry in the downstream server config file, e.g.:
+///
+/// ```yaml
+/// - id: weather
+///   command: ./downstream-weather-server
+/// ```
+#[derive(Debug, Deserialize, Clone)]
+pub struct DownstreamConfig {
+    pub id: String,
+    pub command: String,
+}
+
+/// Loads the list of downstream MCP servers to federate. Missing config is
+/// not an error: downstream aggregation is opt-in, so an absent file just
+/// means there's nothing to federate.
+pub fn load_downstreams<P: AsRef<Path>>(path: P) -> Result<Vec<DownstreamConfig>> {
+    let path = path.as_ref();
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {:?}", path))?;
+
+    serde_yaml::from_str(&content).with_context(|| format!("Failed to parse {:?}", path))
+}
+
+/// A live stdio connection to a spawned downstream. Killed on drop, so the
+/// process goes away with the last `McpClient` handle that holds one.
+///
+/// `stdout` is read on a dedicated background thread that forwards whole
+/// lines over `lines`, so a request can wait on `recv_timeout` instead of
+/// blocking on `read_line` directly: a hung downstream just never produces a
+/// line, and the wait times out instead of parking the caller forever.
+struct Connection {
+    child: Child,
+    stdin: ChildStdin,
+    lines: mpsc::Receiver<String>,
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Speaks MCP as a client to a downstream server. The child is spawned once
+/// and kept alive for the life of the client, so `initialize` runs before
+/// `tools/list`/`tools/call` on the same session instead of a fresh process
+/// per request.
+#[derive(Clone)]
+pub struct McpClient {
+    id: String,
+    command: String,
+    conn: Arc<Mutex<Option<Connection>>>,
+}
+
+impl McpClient {
+    pub fn new(id: impl Into<String>, command: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            command: command.into(),
+            conn: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Runs `initialize` then `tools/list` against the downstream and
+    /// returns its tools with locally-namespaced names.
+    pub fn discover(&self) -> Result<Vec<DownstreamTool>> {
+        self.request(&json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "initialize",
+            "params": {}
+        }))?;
+
+        let response = self.request(&json!({
+            "jsonrpc": "2.0",
+            "id": 2,
+            "method": "tools/list"
+        }))?;
+
+        let tools = response["result"]["tools"].as_array().cloned().unwrap_or_default();
+
+        Ok(tools
+            .into_iter()
+            .filter_map(|tool| {
+                let name = tool["name"].as_str()?;
+                Some(DownstreamTool {
+                    name: format!("{}:{}", self.id, name),
+                    description: tool["description"].as_str().unwrap_or_default().to_string(),
+                    input_schema: tool["inputSchema"].clone(),
+                })
+            })
+            .collect())
+    }
+
+    /// Forwards `tools/call` for `tool` (already stripped of the `id:`
+    /// namespace prefix) to the downstream and returns its result.
+    pub fn call(&self, tool: &str, arguments: &Value) -> Result<Value, String> {
+        let response = self
+            .request(&json!({
+                "jsonrpc": "2.0",
+                "id": 3,
+                "method": "tools/call",
+                "params": { "name": tool, "arguments": arguments }
+            }))
+            .map_err(|err| err.to_string())?;
+
+        if let Some(error) = response.get("error") {
+            let message = error["message"].as_str().unwrap_or("Downstream error");
+            return Err(message.to_string());
+        }
+
+        Ok(response["result"].clone())
+    }
+
+    /// Sends `request` over the persistent connection (spawning it on first
+    /// use) and reads back a single-line response. Requests are serialized
+    /// by the connection lock, same as one ndjson stream would be.
+    fn request(&self, request: &Value) -> Result<Value> {
+        let mut guard = self.conn.lock().unwrap();
+
+        if guard.is_none() {
+            *guard = Some(self.spawn()?);
+        }
+
+        match self.exchange(guard.as_mut().unwrap(), request) {
+            Ok(value) => Ok(value),
+            Err(err) => {
+                // The connection is in an unknown state after a failed
+                // exchange; drop it so the next call respawns cleanly.
+                *guard = None;
+                Err(err)
+            }
+        }
+    }
+
+    fn spawn(&self) -> Result<Connection> {
+        let mut child = ProcessCommand::new(&self.command)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .with_context(|| format!("Failed to spawn downstream '{}'", self.id))?;
+
+        let stdin = child.stdin.take().context("Downstream stdin unavailable")?;
+        let stdout = child.stdout.take().context("Downstream stdout unavailable")?;
+
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            let mut reader = BufReader::new(stdout);
+            loop {
+                let mut line = String::new();
+                match reader.read_line(&mut line) {
+                    Ok(0) | Err(_) => break,
+                    Ok(_) => {
+                        if tx.send(line).is_err() {
+                            break;
+                        }
+                    }
+                }
+            }
+        });
+
+        Ok(Connection { child, stdin, lines: rx })
+    }
+
+    /// Writes `request` and waits up to `DEFAULT_TIMEOUT` for the background
+    /// reader thread to hand back a response line. Times out (rather than
+    /// blocking forever) if the downstream has stopped responding; the
+    /// caller then drops this connection instead of reusing a hung one.
+    fn exchange(&self, conn: &mut Connection, request: &Value) -> Result<Value> {
+        writeln!(conn.stdin, "{}", serde_json::to_string(request)?)
+            .with_context(|| format!("Failed to write to downstream '{}'", self.id))?;
+
+        let line = conn
+            .lines
+            .recv_timeout(DEFAULT_TIMEOUT)
+            .with_context(|| format!("Downstream '{}' timed out", self.id))?;
+
+        serde_json::from_str(line.trim())
+            .with_context(|| format!("Downstream '{}' returned invalid JSON", self.id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    /// Writes a shell script fixture to a uniquely-named temp path so it can
+    /// stand in for a downstream process, and returns the path as a string.
+    fn script(name: &str, contents: &str) -> String {
+        let mut path = std::env::temp_dir();
+        path.push(format!("rapid-mcp-client-test-{}-{}.sh", name, std::process::id()));
+        fs::write(&path, contents).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        path.to_str().unwrap().to_string()
+    }
+
+    /// A fake downstream that answers every request on its persistent
+    /// connection with the same canned `tools/list` result.
+    #[test]
+    fn discover_namespaces_tool_names_with_downstream_id() {
+        let path = script(
+            "discover",
+            "#!/bin/sh\nwhile read -r line; do\n  echo '{\"jsonrpc\":\"2.0\",\"id\":1,\"result\":{\"tools\":[{\"name\":\"forecast\",\"description\":\"Gets the forecast\",\"inputSchema\":{}}]}}'\ndone\n",
+        );
+
+        let client = McpClient::new("weather", &path);
+        let tools = client.discover().unwrap();
+
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0].name, "weather:forecast");
+        assert_eq!(tools[0].description, "Gets the forecast");
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn call_surfaces_downstream_error() {
+        let path = script(
+            "error",
+            "#!/bin/sh\nwhile read -r line; do\n  echo '{\"jsonrpc\":\"2.0\",\"id\":3,\"error\":{\"code\":-1,\"message\":\"boom\"}}'\ndone\n",
+        );
+
+        let client = McpClient::new("weather", &path);
+        let err = client.call("forecast", &json!({})).unwrap_err();
+
+        assert_eq!(err, "boom");
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    /// A downstream that reads one request and then goes silent; `call`
+    /// should time out instead of blocking forever, and the connection
+    /// should be dropped rather than left around for reuse.
+    #[test]
+    fn call_times_out_and_drops_connection_against_a_hanging_downstream() {
+        let path = script("hang", "#!/bin/sh\nread line\nsleep 5\n");
+
+        let client = McpClient::new("weather", &path);
+        let err = client.call("forecast", &json!({})).unwrap_err();
+
+        assert!(err.contains("timed out"));
+        assert!(client.conn.lock().unwrap().is_none());
+
+        fs::remove_file(&path).unwrap();
+    }
+}
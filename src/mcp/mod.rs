@@ -0,0 +1,4 @@
+pub mod client;
+pub mod server;
+pub mod transport;
+pub mod types;
@@ -1,70 +1,164 @@
 use anyhow::Result;
 use serde_json::{json, Value};
-use std::io::{self, BufRead, Write};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::mpsc;
 
+use super::client::McpClient;
+use super::transport::{TransportReader, TransportWriter};
 use super::types::*;
 use crate::command::loader::CommandRegistry;
+use crate::command::plugin::PluginClient;
 
+#[derive(Clone)]
 pub struct McpServer {
-    registry: CommandRegistry,
+    registry: Arc<CommandRegistry>,
+    commands_dir: String,
+    downstreams: Arc<HashMap<String, McpClient>>,
 }
 
 impl McpServer {
-    pub fn new(registry: CommandRegistry) -> Self {
-        Self { registry }
+    pub fn new(
+        registry: CommandRegistry,
+        commands_dir: impl Into<String>,
+        downstreams: HashMap<String, McpClient>,
+    ) -> Self {
+        Self {
+            registry: Arc::new(registry),
+            commands_dir: commands_dir.into(),
+            downstreams: Arc::new(downstreams),
+        }
     }
 
-    pub fn run(&self) -> Result<()> {
-        let stdin = io::stdin();
-        let mut stdout = io::stdout();
-
-        for line in stdin.lock().lines() {
-            let line = line?;
+    /// Reads messages via `reader`, dispatching each onto its own task so a
+    /// slow tool call can't stall the rest. Responses are funneled through
+    /// a single writer task driving `writer`, so framing stays correct even
+    /// when tasks finish out of order. Transport-agnostic: `reader`/`writer`
+    /// decide whether messages are ndjson lines or `Content-Length` frames.
+    pub async fn run(
+        &self,
+        mut reader: Box<dyn TransportReader>,
+        mut writer: Box<dyn TransportWriter>,
+    ) -> Result<()> {
+        let (tx, mut rx) = mpsc::unbounded_channel::<String>();
 
-            if let Ok(request) = serde_json::from_str::<JsonRpcRequest>(&line) {
-                let response = self.handle_request(&request);
+        let writer_task = tokio::spawn(async move {
+            while let Some(message) = rx.recv().await {
+                writer.write_message(&message).await?;
+            }
+            Ok::<(), anyhow::Error>(())
+        });
 
-                let response_json = serde_json::to_string(&response)?;
-                writeln!(stdout, "{}", response_json)?;
-                stdout.flush()?;
+        let notify_tx = tx.clone();
+        self.registry.watch(&self.commands_dir, move || {
+            let notification = json!({
+                "jsonrpc": "2.0",
+                "method": "notifications/tools/list_changed"
+            });
+            if let Ok(message) = serde_json::to_string(&notification) {
+                let _ = notify_tx.send(message);
             }
+        })?;
+
+        while let Some(message) = reader.read_message().await? {
+            let server = self.clone();
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                if let Some(output) = server.handle_line(&message).await {
+                    let _ = tx.send(output);
+                }
+            });
         }
 
+        drop(tx);
+        writer_task.await??;
+
         Ok(())
     }
 
-    fn handle_request(&self, request: &JsonRpcRequest) -> JsonRpcResponse {
+    /// Parses one ndjson line and returns the serialized response to write,
+    /// or `None` when the line produced nothing to send (pure notifications).
+    async fn handle_line(&self, line: &str) -> Option<String> {
+        let value: Value = match serde_json::from_str(line) {
+            Ok(value) => value,
+            Err(_) => {
+                let response = JsonRpcResponse::error(None, PARSE_ERROR, "Parse error");
+                return serde_json::to_string(&response).ok();
+            }
+        };
+
+        match value {
+            Value::Array(items) => {
+                if items.is_empty() {
+                    let response =
+                        JsonRpcResponse::error(None, INVALID_REQUEST, "Invalid Request");
+                    return serde_json::to_string(&response).ok();
+                }
+
+                let mut responses = Vec::new();
+                for item in items {
+                    if let Some(response) = self.handle_value(item).await {
+                        if let Ok(value) = serde_json::to_value(response) {
+                            responses.push(value);
+                        }
+                    }
+                }
+
+                if responses.is_empty() {
+                    None
+                } else {
+                    serde_json::to_string(&responses).ok()
+                }
+            }
+            value => {
+                let response = self.handle_value(value).await?;
+                serde_json::to_string(&response).ok()
+            }
+        }
+    }
+
+    /// Validates and dispatches a single already-parsed JSON-RPC envelope,
+    /// returning `None` for notifications (requests with no `id`).
+    async fn handle_value(&self, value: Value) -> Option<JsonRpcResponse> {
+        let request: JsonRpcRequest = match serde_json::from_value::<JsonRpcRequest>(value) {
+            Ok(request) if !request.jsonrpc.is_empty() && !request.method.is_empty() => request,
+            _ => {
+                return Some(JsonRpcResponse::error(
+                    None,
+                    INVALID_REQUEST,
+                    "Invalid Request",
+                ))
+            }
+        };
+
+        self.handle_request(&request).await
+    }
+
+    async fn handle_request(&self, request: &JsonRpcRequest) -> Option<JsonRpcResponse> {
+        let id = request.id.clone();
+
         let result = match request.method.as_str() {
             "initialize" => self.handle_initialize(),
             "tools/list" => self.handle_tools_list(),
-            "tools/call" => self.handle_tools_call(request.params.as_ref()),
-            _ => Err(format!("Unknown method: {}", request.method)),
+            "tools/call" => self.handle_tools_call(request.params.as_ref()).await,
+            _ => Err((METHOD_NOT_FOUND, format!("Unknown method: {}", request.method))),
         };
 
-        match result {
-            Ok(value) => JsonRpcResponse {
-                jsonrpc: "2.0".to_string(),
-                id: request.id,
-                result: Some(value),
-                error: None,
-            },
-            Err(msg) => JsonRpcResponse {
-                jsonrpc: "2.0".to_string(),
-                id: request.id,
-                result: None,
-                error: Some(JsonRpcError {
-                    code: -32603,
-                    message: msg,
-                }),
-            },
-        }
+        // A request with no `id` is a notification: execute it, but the
+        // spec forbids replying, even on error.
+        let id = id?;
+
+        Some(match result {
+            Ok(value) => JsonRpcResponse::success(Some(id), value),
+            Err((code, message)) => JsonRpcResponse::error(Some(id), code, message),
+        })
     }
 
-    fn handle_initialize(&self) -> Result<Value, String> {
+    fn handle_initialize(&self) -> Result<Value, (i64, String)> {
         Ok(json!({
             "protocolVersion": "2024-11-05",
             "capabilities": {
-                "tools": {}
+                "tools": { "listChanged": true }
             },
             "serverInfo": {
                 "name": "rapid-mcp-server-rust",
@@ -73,18 +167,14 @@ impl McpServer {
         }))
     }
 
-    fn handle_tools_list(&self) -> Result<Value, String> {
+    fn handle_tools_list(&self) -> Result<Value, (i64, String)> {
         let tools: Vec<Value> = self.registry.list()
             .iter()
             .map(|cmd| {
                 json!({
                     "name": cmd.name,
                     "description": cmd.description,
-                    "inputSchema": {
-                        "type": "object",
-                        "properties": {},
-                        "required": []
-                    }
+                    "inputSchema": cmd.input_schema()
                 })
             })
             .collect();
@@ -92,21 +182,128 @@ impl McpServer {
         Ok(json!({ "tools": tools }))
     }
 
-    fn handle_tools_call(&self, params: Option<&Value>) -> Result<Value, String> {
-        let params = params.ok_or("Missing params")?;
-        let tool_name = params["name"].as_str().ok_or("Missing tool name")?;
+    async fn handle_tools_call(&self, params: Option<&Value>) -> Result<Value, (i64, String)> {
+        let params = params.ok_or((INVALID_PARAMS, "Missing params".to_string()))?;
+        let tool_name = params["name"]
+            .as_str()
+            .ok_or((INVALID_PARAMS, "Missing tool name".to_string()))?
+            .to_string();
 
-        let command = self.registry.get(tool_name)
-            .ok_or_else(|| format!("Unknown tool: {}", tool_name))?;
+        let command = self.registry.get(&tool_name)
+            .ok_or_else(|| (INVALID_PARAMS, format!("Unknown tool: {}", tool_name)))?;
 
-        // For MVP, just return the prompt template
-        // TODO: Substitute parameters using handlebars
+        let arguments = params.get("arguments").cloned().unwrap_or_else(|| json!({}));
+
+        if let Some(plugin_path) = command.plugin.clone() {
+            return tokio::task::spawn_blocking(move || {
+                PluginClient::new(plugin_path).call(&tool_name, &arguments)
+            })
+            .await
+            .map_err(|err| (INTERNAL_ERROR, err.to_string()))?
+            .map_err(|err| (INTERNAL_ERROR, err));
+        }
+
+        if let Some(downstream_id) = command.downstream.clone() {
+            let client = self.downstreams.get(&downstream_id)
+                .ok_or_else(|| (INTERNAL_ERROR, format!("Unknown downstream: {}", downstream_id)))?
+                .clone();
+            let upstream_name = tool_name
+                .strip_prefix(&format!("{}:", downstream_id))
+                .unwrap_or(&tool_name)
+                .to_string();
+
+            return tokio::task::spawn_blocking(move || client.call(&upstream_name, &arguments))
+                .await
+                .map_err(|err| (INTERNAL_ERROR, err.to_string()))?
+                .map_err(|err| (INTERNAL_ERROR, err));
+        }
+
+        let text = self.registry.render(&tool_name, &arguments).map_err(|missing| {
+            (
+                INVALID_PARAMS,
+                format!("Missing required parameters: {}", missing.join(", ")),
+            )
+        })?;
 
         Ok(json!({
             "content": [{
                 "type": "text",
-                "text": command.prompt
+                "text": text
             }]
         }))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::loader::CommandRegistry;
+
+    fn server() -> McpServer {
+        McpServer::new(CommandRegistry::new(), "commands".to_string(), HashMap::new())
+    }
+
+    #[tokio::test]
+    async fn handle_line_returns_response_for_request_with_id() {
+        let output = server()
+            .handle_line(r#"{"jsonrpc":"2.0","id":1,"method":"tools/list"}"#)
+            .await
+            .expect("request with id must get a response");
+
+        let response: Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(response["id"], json!(1));
+        assert!(response["result"]["tools"].is_array());
+    }
+
+    #[tokio::test]
+    async fn handle_line_returns_nothing_for_notification() {
+        let output = server()
+            .handle_line(r#"{"jsonrpc":"2.0","method":"tools/list"}"#)
+            .await;
+
+        assert!(output.is_none());
+    }
+
+    #[tokio::test]
+    async fn handle_line_reports_parse_error_for_invalid_json() {
+        let output = server().handle_line("not json").await.unwrap();
+        let response: Value = serde_json::from_str(&output).unwrap();
+
+        assert_eq!(response["error"]["code"], json!(PARSE_ERROR));
+        assert_eq!(response["id"], Value::Null);
+    }
+
+    #[tokio::test]
+    async fn handle_line_reports_method_not_found() {
+        let output = server()
+            .handle_line(r#"{"jsonrpc":"2.0","id":1,"method":"bogus"}"#)
+            .await
+            .unwrap();
+        let response: Value = serde_json::from_str(&output).unwrap();
+
+        assert_eq!(response["error"]["code"], json!(METHOD_NOT_FOUND));
+    }
+
+    #[tokio::test]
+    async fn handle_line_rejects_empty_batch() {
+        let output = server().handle_line("[]").await.unwrap();
+        let response: Value = serde_json::from_str(&output).unwrap();
+
+        assert_eq!(response["error"]["code"], json!(INVALID_REQUEST));
+    }
+
+    #[tokio::test]
+    async fn handle_line_handles_batch_of_requests_and_notifications() {
+        let batch = r#"[
+            {"jsonrpc":"2.0","id":1,"method":"tools/list"},
+            {"jsonrpc":"2.0","method":"tools/list"}
+        ]"#;
+
+        let output = server().handle_line(batch).await.unwrap();
+        let responses: Vec<Value> = serde_json::from_str(&output).unwrap();
+
+        // Only the request with an id produces an entry; the notification is silent.
+        assert_eq!(responses.len(), 1);
+        assert_eq!(responses[0]["id"], json!(1));
+    }
+}
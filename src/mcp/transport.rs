@@ -0,0 +1,253 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use tokio::io::{
+    AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWriteExt, BufReader, Lines, Stdin, Stdout,
+};
+
+/// How the server frames messages on stdio. The dispatch logic in
+/// `McpServer` never sees raw bytes, only whole message bodies, so it stays
+/// the same regardless of which framing is in effect.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransportMode {
+    /// One JSON value per line — the MCP/JSON-RPC ndjson convention.
+    Ndjson,
+    /// `Content-Length`-framed, one header block then a raw JSON body.
+    Header,
+}
+
+impl TransportMode {
+    /// Reads `--transport=<mode>` out of `args`, falling back to the
+    /// `MCP_TRANSPORT` env var, defaulting to `Ndjson`.
+    pub fn from_args_or_env(args: &[String]) -> Self {
+        let flag = args.iter().find_map(|arg| arg.strip_prefix("--transport="));
+        let value = flag
+            .map(str::to_string)
+            .or_else(|| std::env::var("MCP_TRANSPORT").ok());
+
+        match value.as_deref() {
+            Some("header") => TransportMode::Header,
+            _ => TransportMode::Ndjson,
+        }
+    }
+
+    pub fn build(self) -> (Box<dyn TransportReader>, Box<dyn TransportWriter>) {
+        match self {
+            TransportMode::Ndjson => (
+                Box::new(NdjsonReader::new()) as Box<dyn TransportReader>,
+                Box::new(NdjsonWriter::new()) as Box<dyn TransportWriter>,
+            ),
+            TransportMode::Header => (
+                Box::new(HeaderReader::new()) as Box<dyn TransportReader>,
+                Box::new(HeaderWriter::new()) as Box<dyn TransportWriter>,
+            ),
+        }
+    }
+}
+
+#[async_trait]
+pub trait TransportReader: Send {
+    /// Reads the next message body, or `None` at EOF.
+    async fn read_message(&mut self) -> Result<Option<String>>;
+}
+
+#[async_trait]
+pub trait TransportWriter: Send {
+    async fn write_message(&mut self, message: &str) -> Result<()>;
+}
+
+pub struct NdjsonReader {
+    lines: Lines<BufReader<Stdin>>,
+}
+
+impl NdjsonReader {
+    pub fn new() -> Self {
+        Self {
+            lines: BufReader::new(tokio::io::stdin()).lines(),
+        }
+    }
+}
+
+#[async_trait]
+impl TransportReader for NdjsonReader {
+    async fn read_message(&mut self) -> Result<Option<String>> {
+        while let Some(line) = self.lines.next_line().await? {
+            if !line.trim().is_empty() {
+                return Ok(Some(line));
+            }
+        }
+        Ok(None)
+    }
+}
+
+pub struct NdjsonWriter {
+    stdout: Stdout,
+}
+
+impl NdjsonWriter {
+    pub fn new() -> Self {
+        Self {
+            stdout: tokio::io::stdout(),
+        }
+    }
+}
+
+#[async_trait]
+impl TransportWriter for NdjsonWriter {
+    async fn write_message(&mut self, message: &str) -> Result<()> {
+        self.stdout.write_all(message.as_bytes()).await?;
+        self.stdout.write_all(b"\n").await?;
+        self.stdout.flush().await?;
+        Ok(())
+    }
+}
+
+/// Generic over the underlying reader (defaulting to stdin in production) so
+/// the framing logic in `read_message` can be exercised against an in-memory
+/// buffer in tests instead of real stdin.
+pub struct HeaderReader<R = Stdin> {
+    reader: BufReader<R>,
+}
+
+impl HeaderReader<Stdin> {
+    pub fn new() -> Self {
+        Self {
+            reader: BufReader::new(tokio::io::stdin()),
+        }
+    }
+}
+
+impl<R: AsyncRead + Unpin> HeaderReader<R> {
+    #[cfg(test)]
+    fn from_reader(reader: R) -> Self {
+        Self {
+            reader: BufReader::new(reader),
+        }
+    }
+}
+
+#[async_trait]
+impl<R: AsyncRead + Unpin + Send> TransportReader for HeaderReader<R> {
+    async fn read_message(&mut self) -> Result<Option<String>> {
+        let mut content_length: Option<usize> = None;
+
+        loop {
+            let mut header = String::new();
+            let bytes_read = self.reader.read_line(&mut header).await?;
+            if bytes_read == 0 {
+                return Ok(None);
+            }
+
+            let header = header.trim_end_matches(['\r', '\n']);
+            if header.is_empty() {
+                break;
+            }
+
+            if let Some((name, value)) = header.split_once(':') {
+                if name.trim().eq_ignore_ascii_case("content-length") {
+                    content_length = value.trim().parse().ok();
+                }
+            }
+        }
+
+        // A missing/malformed Content-Length means we don't know how many
+        // body bytes belong to this frame, so there's nothing sane to read.
+        // Rather than failing the whole loop over one bad frame (and taking
+        // the server down with it), hand back an empty body: it fails JSON
+        // parsing in `handle_line` the same way a bad ndjson line does,
+        // producing a single Parse error response while the loop carries on.
+        let content_length = match content_length {
+            Some(len) => len,
+            None => return Ok(Some(String::new())),
+        };
+
+        let mut body = vec![0u8; content_length];
+        if self.reader.read_exact(&mut body).await.is_err() {
+            return Ok(Some(String::new()));
+        }
+
+        Ok(Some(String::from_utf8(body).unwrap_or_default()))
+    }
+}
+
+pub struct HeaderWriter {
+    stdout: Stdout,
+}
+
+impl HeaderWriter {
+    pub fn new() -> Self {
+        Self {
+            stdout: tokio::io::stdout(),
+        }
+    }
+}
+
+#[async_trait]
+impl TransportWriter for HeaderWriter {
+    async fn write_message(&mut self, message: &str) -> Result<()> {
+        let header = format!("Content-Length: {}\r\n\r\n", message.len());
+        self.stdout.write_all(header.as_bytes()).await?;
+        self.stdout.write_all(message.as_bytes()).await?;
+        self.stdout.flush().await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Feeds `bytes` to a `HeaderReader` over an in-memory duplex stream,
+    /// closing the write half so EOF follows the last byte.
+    async fn reader_with(bytes: &[u8]) -> HeaderReader<tokio::io::DuplexStream> {
+        let (mut writer, reader) = tokio::io::duplex(bytes.len().max(1));
+        writer.write_all(bytes).await.unwrap();
+        drop(writer);
+        HeaderReader::from_reader(reader)
+    }
+
+    #[tokio::test]
+    async fn read_message_parses_a_well_formed_frame() {
+        let body = r#"{"jsonrpc":"2.0","id":1,"method":"tools/list"}"#;
+        let frame = format!("Content-Length: {}\r\n\r\n{}", body.len(), body);
+
+        let message = reader_with(frame.as_bytes())
+            .await
+            .read_message()
+            .await
+            .unwrap();
+
+        assert_eq!(message, Some(body.to_string()));
+    }
+
+    #[tokio::test]
+    async fn read_message_returns_empty_body_for_missing_content_length() {
+        let frame = "Foo: bar\r\n\r\nirrelevant body";
+
+        let message = reader_with(frame.as_bytes())
+            .await
+            .read_message()
+            .await
+            .unwrap();
+
+        assert_eq!(message, Some(String::new()));
+    }
+
+    #[tokio::test]
+    async fn read_message_returns_empty_body_for_truncated_body() {
+        let frame = "Content-Length: 100\r\n\r\ntoo short";
+
+        let message = reader_with(frame.as_bytes())
+            .await
+            .read_message()
+            .await
+            .unwrap();
+
+        assert_eq!(message, Some(String::new()));
+    }
+
+    #[tokio::test]
+    async fn read_message_returns_none_at_eof() {
+        let message = reader_with(b"").await.read_message().await.unwrap();
+        assert_eq!(message, None);
+    }
+}